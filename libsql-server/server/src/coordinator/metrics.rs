@@ -0,0 +1,256 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::job::{Job, JobBatch};
+
+/// Upper bounds of each query latency bucket, in microseconds. An observation
+/// greater than every bound here still falls somewhere: into one trailing
+/// unbounded bucket. Chosen to span sub-millisecond queries up through
+/// second-long ones without needing so many buckets that each is too sparse
+/// to be useful.
+const LATENCY_BUCKETS_MICROS: [u64; 9] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+/// Counters for a single [`Worker`](super::Worker): how many queries it has
+/// run and how long they took, plus how its transactions ended. Lives behind
+/// an `Arc` so the worker thread and a caller reading [`Coordinator::metrics`]
+/// see the same counters.
+pub struct WorkerMetrics {
+    queries: AtomicU64,
+    query_micros: AtomicU64,
+    /// one counter per [`LATENCY_BUCKETS_MICROS`] entry plus a trailing
+    /// unbounded bucket, each holding only the queries that landed in that
+    /// specific bucket (not cumulative). See [`WorkerMetrics::query_latency_histogram`].
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS_MICROS.len() + 1],
+    txn_opened: AtomicU64,
+    txn_committed: AtomicU64,
+    txn_rolled_back: AtomicU64,
+    txn_timed_out: AtomicU64,
+}
+
+impl Default for WorkerMetrics {
+    fn default() -> Self {
+        Self {
+            queries: AtomicU64::new(0),
+            query_micros: AtomicU64::new(0),
+            latency_buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            txn_opened: AtomicU64::new(0),
+            txn_committed: AtomicU64::new(0),
+            txn_rolled_back: AtomicU64::new(0),
+            txn_timed_out: AtomicU64::new(0),
+        }
+    }
+}
+
+impl WorkerMetrics {
+    pub(super) fn record_query(&self, elapsed: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        let micros = elapsed.as_micros() as u64;
+        self.query_micros.fetch_add(micros, Ordering::Relaxed);
+        let bucket = LATENCY_BUCKETS_MICROS
+            .iter()
+            .position(|&bound| micros <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MICROS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_txn_opened(&self) {
+        self.txn_opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_txn_committed(&self) {
+        self.txn_committed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_txn_rolled_back(&self) {
+        self.txn_rolled_back.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_txn_timed_out(&self) {
+        self.txn_timed_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of queries run so far.
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    /// Average query latency observed so far, or `None` if no query has run yet.
+    pub fn avg_query_latency(&self) -> Option<Duration> {
+        let n = self.queries();
+        (n > 0).then(|| Duration::from_micros(self.query_micros.load(Ordering::Relaxed) / n))
+    }
+
+    /// Snapshot of the query latency histogram: one `(upper_bound, count)`
+    /// pair per [`LATENCY_BUCKETS_MICROS`] entry, plus a trailing
+    /// `(None, count)` for everything slower than the last bound. Each count
+    /// is the number of queries that landed in that specific bucket, not a
+    /// running total, so an exporter can turn this into a Prometheus-style
+    /// cumulative histogram by summing as it goes.
+    pub fn query_latency_histogram(&self) -> Vec<(Option<Duration>, u64)> {
+        LATENCY_BUCKETS_MICROS
+            .iter()
+            .map(|&bound| Some(Duration::from_micros(bound)))
+            .chain(std::iter::once(None))
+            .zip(self.latency_buckets.iter().map(|c| c.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Estimates the latency under which `p` (e.g. `0.99` for p99) of
+    /// recorded queries finished, by walking the histogram buckets until
+    /// their running total reaches `p * queries()`. The result is the upper
+    /// bound of whichever bucket that happens in, so it's a conservative
+    /// (rounded up to the nearest bucket boundary) estimate rather than an
+    /// interpolated one. If the target rank falls in the trailing unbounded
+    /// bucket, the slowest finite bound is returned instead, which is lower
+    /// than the real latency. Returns `None` if no query has run yet.
+    pub fn query_latency_percentile(&self, p: f64) -> Option<Duration> {
+        let n = self.queries();
+        if n == 0 {
+            return None;
+        }
+        let target = (p.clamp(0.0, 1.0) * n as f64).ceil().max(1.0) as u64;
+        let mut cumulative = 0u64;
+        for (&bound, count) in LATENCY_BUCKETS_MICROS.iter().zip(self.latency_buckets.iter()) {
+            cumulative += count.load(Ordering::Relaxed);
+            if cumulative >= target {
+                return Some(Duration::from_micros(bound));
+            }
+        }
+        // every finite bucket was exhausted, so the target rank falls in the
+        // trailing unbounded bucket: there's no upper bound to report, so
+        // fall back to the slowest finite boundary as a floor.
+        LATENCY_BUCKETS_MICROS.last().map(|&b| Duration::from_micros(b))
+    }
+
+    pub fn txn_opened(&self) -> u64 {
+        self.txn_opened.load(Ordering::Relaxed)
+    }
+
+    pub fn txn_committed(&self) -> u64 {
+        self.txn_committed.load(Ordering::Relaxed)
+    }
+
+    pub fn txn_rolled_back(&self) -> u64 {
+        self.txn_rolled_back.load(Ordering::Relaxed)
+    }
+
+    pub fn txn_timed_out(&self) -> u64 {
+        self.txn_timed_out.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn avg_query_latency_is_none_until_a_query_runs() {
+        let m = WorkerMetrics::default();
+        assert_eq!(m.avg_query_latency(), None);
+        assert_eq!(m.query_latency_percentile(0.5), None);
+    }
+
+    #[test]
+    fn record_query_buckets_and_averages_latency() {
+        let m = WorkerMetrics::default();
+        m.record_query(Duration::from_micros(50));
+        m.record_query(Duration::from_micros(50));
+        m.record_query(Duration::from_millis(2));
+
+        assert_eq!(m.queries(), 3);
+        assert_eq!(m.avg_query_latency(), Some(Duration::from_micros(700)));
+
+        let hist = m.query_latency_histogram();
+        // two observations at 50us fall in the first (<=100us) bucket...
+        assert_eq!(hist[0], (Some(Duration::from_micros(100)), 2));
+        // ...and the 2ms observation in the (<=5000us) bucket.
+        assert_eq!(hist[3], (Some(Duration::from_micros(5_000)), 1));
+        // every other bucket, including the trailing unbounded one, is empty.
+        assert!(hist
+            .iter()
+            .enumerate()
+            .all(|(i, &(_, count))| i == 0 || i == 3 || count == 0));
+    }
+
+    #[test]
+    fn query_latency_percentile_reports_the_bucket_bound_not_an_interpolation() {
+        let m = WorkerMetrics::default();
+        for _ in 0..9 {
+            m.record_query(Duration::from_micros(50));
+        }
+        m.record_query(Duration::from_millis(800));
+
+        // rank 5 of 10 is still one of the 9 fast queries.
+        assert_eq!(m.query_latency_percentile(0.5), Some(Duration::from_micros(100)));
+        // rank 10 of 10 is the single slow outlier, in the last finite bucket.
+        assert_eq!(
+            m.query_latency_percentile(0.91),
+            Some(Duration::from_micros(1_000_000))
+        );
+    }
+
+    #[test]
+    fn query_latency_percentile_falls_back_to_the_slowest_bound_past_every_bucket() {
+        let m = WorkerMetrics::default();
+        m.record_query(Duration::from_secs(10));
+        assert_eq!(
+            m.query_latency_percentile(1.0),
+            Some(Duration::from_micros(*LATENCY_BUCKETS_MICROS.last().unwrap()))
+        );
+    }
+}
+
+/// How many jobs are currently waiting in each of the coordinator's queues.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueDepth {
+    pub read: usize,
+    pub write: usize,
+    pub batch: usize,
+}
+
+/// Observability for a [`Coordinator`](super::Coordinator): per-worker query
+/// counts and latency, transaction lifecycle tallies, and how deep each
+/// queue currently is. Handed out behind an `Arc`, so it is cheap to share
+/// across threads.
+pub struct Metrics {
+    /// indexed by worker id, i.e. `0..ncpu` are the read workers and `ncpu`
+    /// is the single write worker, matching how [`Coordinator::new`] assigns ids.
+    workers: Vec<Arc<WorkerMetrics>>,
+    read_fifo: crossbeam::channel::Sender<Job>,
+    write_fifo: crossbeam::channel::Sender<Job>,
+    batch_fifo: crossbeam::channel::Sender<JobBatch>,
+}
+
+impl Metrics {
+    pub(super) fn new(
+        workers: Vec<Arc<WorkerMetrics>>,
+        read_fifo: crossbeam::channel::Sender<Job>,
+        write_fifo: crossbeam::channel::Sender<Job>,
+        batch_fifo: crossbeam::channel::Sender<JobBatch>,
+    ) -> Self {
+        Self {
+            workers,
+            read_fifo,
+            write_fifo,
+            batch_fifo,
+        }
+    }
+
+    /// Per-worker counters, indexed by worker id (the read pool first, then
+    /// the write worker).
+    pub fn workers(&self) -> &[Arc<WorkerMetrics>] {
+        &self.workers
+    }
+
+    /// How many jobs are currently sitting in each queue, waiting for a worker.
+    pub fn queue_depth(&self) -> QueueDepth {
+        QueueDepth {
+            read: self.read_fifo.len(),
+            write: self.write_fifo.len(),
+            batch: self.batch_fifo.len(),
+        }
+    }
+}
@@ -1,7 +1,9 @@
+pub mod metrics;
 pub mod query;
 pub mod scheduler;
 pub mod statements;
 
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
@@ -9,50 +11,163 @@ use crossbeam::channel::Sender;
 use futures::stream::FuturesUnordered;
 use tokio::task::JoinHandle;
 
-use crate::coordinator::query::{ErrorCode, QueryError, QueryResponse, QueryResult};
-use crate::coordinator::scheduler::UpdateStateMessage;
+use crate::coordinator::metrics::{Metrics, WorkerMetrics};
+use crate::coordinator::query::{ColumnMeta, ErrorCode, QueryError, QueryResponse, QueryResult};
+use crate::coordinator::scheduler::{DelayedQueue, JobId, UpdateStateMessage};
 use crate::coordinator::statements::{State, Statements};
-use crate::job::Job;
+use crate::job::{Job, JobBatch};
 use crate::wal::WalConnection;
 
 const TXN_TIMEOUT_SECS: usize = 5;
 
 /// Transaction coordinator.
+///
+/// Internally, jobs are split across two pools: a read pool of `ncpu` workers
+/// that each hold a read-only connection and can run read-only jobs in
+/// parallel, and a single dedicated write worker that serializes writes and
+/// interactive transactions, as WAL mode requires. Callers see none of this:
+/// they still submit every [`Job`] through the one `Sender<Job>` returned
+/// here, and a dispatcher routes each job to the right pool. A [`JobBatch`]
+/// bypasses the dispatcher entirely and goes straight to the write worker
+/// through the `Sender<JobBatch>` returned alongside it, since a batch is a
+/// bulk write and WAL allows only one writer.
 pub struct Coordinator {
     worker_handles: FuturesUnordered<JoinHandle<()>>,
+    delayed: Arc<DelayedQueue>,
+    metrics: Arc<Metrics>,
 }
 
 impl Coordinator {
-    /// Create a new coordinator that will spawn `ncpu` threads.
-    /// Each worker maintains a connections to the database, and process jobs sequentially.
-    /// `conn_builder` must create a fresh db_connection each time it is called.
-    /// If ncpu is 0, then the number of worker is determined automatically.
+    /// Create a new coordinator with a read pool of `ncpu` workers and one
+    /// dedicated write worker.
+    /// `conn_builder` must create a fresh read-write db connection each time
+    /// it is called, and `read_conn_builder` must do the same for a
+    /// `SQLITE_OPEN_READONLY` connection. `retry_policy` governs how workers
+    /// retry statements that fail with a transient `SQLITE_BUSY`/`SQLITE_LOCKED`
+    /// error.
+    /// If ncpu is 0, then the number of read workers is determined automatically.
+    /// Returns the coordinator along with the `Sender<Job>` and `Sender<JobBatch>`
+    /// callers submit work through.
     pub fn new(
         mut ncpu: usize,
-        conn_builder: impl Fn() -> WalConnection + Sync + Send,
-    ) -> Result<(Self, Sender<Job>)> {
+        conn_builder: impl Fn() -> WalConnection + Sync + Send + 'static,
+        read_conn_builder: impl Fn() -> WalConnection + Sync + Send + 'static,
+        retry_policy: RetryPolicy,
+    ) -> Result<(Self, Sender<Job>, Sender<JobBatch>)> {
         if ncpu == 0 {
             ncpu = std::thread::available_parallelism()?.get();
         }
-        let (fifo, receiver) = crossbeam::channel::unbounded();
+        let (fifo, global_receiver) = crossbeam::channel::unbounded::<Job>();
+        let (read_fifo, read_receiver) = crossbeam::channel::unbounded::<Job>();
+        let (write_fifo, write_receiver) = crossbeam::channel::unbounded::<Job>();
+        let (batch_fifo, batch_receiver) = crossbeam::channel::unbounded::<JobBatch>();
+        let delayed = Arc::new(DelayedQueue::new());
 
         let worker_handles = FuturesUnordered::new();
+
+        // Dispatcher: holds back any job whose `run_at` hasn't come yet, and
+        // otherwise classifies the job and routes it to the read pool or to
+        // the single writer, so callers never have to know about either split.
+        let dispatch_delayed = delayed.clone();
+        let dispatch_read_fifo = read_fifo.clone();
+        let dispatch_write_fifo = write_fifo.clone();
+        worker_handles.push(tokio::task::spawn_blocking(move || {
+            let read_fifo = dispatch_read_fifo;
+            let write_fifo = dispatch_write_fifo;
+            while let Ok(job) = global_receiver.recv() {
+                if let Some(at) = job.run_at {
+                    if at > Instant::now() {
+                        dispatch_delayed.schedule(at, job);
+                        continue;
+                    }
+                }
+                let pool = match classify(&job.statements) {
+                    WorkerKind::Read => &read_fifo,
+                    WorkerKind::Write => &write_fifo,
+                };
+                if pool.send(job).is_err() {
+                    break;
+                }
+            }
+        }));
+
+        // Timer: wakes whenever a delayed job comes due (or a sooner one is
+        // scheduled) and feeds it back into the global fifo for dispatch.
+        let timer_delayed = delayed.clone();
+        let timer_fifo = fifo.clone();
+        worker_handles.push(tokio::task::spawn_blocking(move || loop {
+            let (due, next) = timer_delayed.drain_due(Instant::now());
+            for mut job in due {
+                job.run_at = None;
+                if timer_fifo.send(job).is_err() {
+                    return;
+                }
+            }
+            timer_delayed.wait_until(next);
+        }));
+
+        let mut worker_metrics = Vec::with_capacity(ncpu + 1);
+
         for id in 0..ncpu {
-            let db_conn = conn_builder();
-            let global_fifo = receiver.clone();
+            let db_conn = read_conn_builder();
+            let global_fifo = read_receiver.clone();
+            let metrics = Arc::new(WorkerMetrics::default());
+            worker_metrics.push(metrics.clone());
             worker_handles.push(tokio::task::spawn_blocking(move || {
                 let worker = Worker {
+                    kind: WorkerKind::Read,
                     global_fifo,
+                    batch_fifo: None,
                     db_conn,
                     id,
+                    retry_policy,
+                    metrics,
                 };
 
                 worker.run();
             }));
         }
 
-        let this = Self { worker_handles };
-        Ok((this, fifo))
+        // A single writer keeps writes and interactive transactions
+        // serialized, matching WAL's single-writer invariant. Batches are
+        // bulk writes too, so they're drained by this same worker rather
+        // than getting a pool of their own.
+        let write_conn = conn_builder();
+        let write_metrics = Arc::new(WorkerMetrics::default());
+        worker_metrics.push(write_metrics.clone());
+        worker_handles.push(tokio::task::spawn_blocking(move || {
+            let worker = Worker {
+                kind: WorkerKind::Write,
+                global_fifo: write_receiver,
+                batch_fifo: Some(batch_receiver),
+                db_conn: write_conn,
+                id: ncpu,
+                retry_policy,
+                metrics: write_metrics,
+            };
+
+            worker.run();
+        }));
+
+        let metrics = Arc::new(Metrics::new(
+            worker_metrics,
+            read_fifo,
+            write_fifo,
+            batch_fifo.clone(),
+        ));
+
+        let this = Self {
+            worker_handles,
+            delayed,
+            metrics,
+        };
+        Ok((this, fifo, batch_fifo))
+    }
+
+    /// Observability counters: per-worker query counts and latency,
+    /// transaction lifecycle tallies, and queue depths.
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
     }
 
     /// waits for all workers to finish their work and exit.
@@ -63,62 +178,317 @@ impl Coordinator {
             }
         }
     }
+
+    /// Schedules `job` to run at `run_at` instead of immediately, e.g. for a
+    /// periodic VACUUM or cache-eviction query. Returns an id that can be
+    /// passed to [`Coordinator::cancel_scheduled`].
+    pub fn schedule(&self, run_at: Instant, job: Job) -> JobId {
+        self.delayed.schedule(run_at, job)
+    }
+
+    /// Cancels a job previously scheduled with [`Coordinator::schedule`].
+    /// Returns `false` if it already ran, was already cancelled, or `id` is
+    /// unknown.
+    pub fn cancel_scheduled(&self, id: JobId) -> bool {
+        self.delayed.cancel(id)
+    }
 }
 
 impl std::convert::From<rusqlite::Error> for QueryError {
     fn from(err: rusqlite::Error) -> Self {
-        QueryError::new(ErrorCode::SQLError, err)
+        let code = match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                ErrorCode::TxBusy
+            }
+            _ => ErrorCode::SQLError,
+        };
+        QueryError::new(code, err)
+    }
+}
+
+/// Controls how a [`Worker`] retries a statement batch that failed with a
+/// transient `SQLITE_BUSY`/`SQLITE_LOCKED` error: up to `max_retries` times,
+/// waiting `base_delay * 2^attempt` plus a small random jitter between tries.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+/// A few milliseconds of jitter to avoid a thundering herd of retries all
+/// waking up at the same instant, without pulling in a `rand` dependency.
+fn jitter_millis(max: u64) -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    u64::from(nanos) % max.max(1)
+}
+
+/// How long to wait before retry number `attempt` (0-indexed): `base_delay`
+/// doubled once per attempt, plus a few milliseconds of jitter.
+fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    policy.base_delay * 2u32.pow(attempt) + Duration::from_millis(jitter_millis(10))
+}
+
+/// Which pool a [`Worker`] belongs to. Used by the dispatcher to pick a fifo
+/// for a given job (see [`classify`]); on a [`Worker`] itself it's only used
+/// for logging, since the split is actually enforced by which fifo a worker
+/// drains, not by this field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorkerKind {
+    Read,
+    Write,
+}
+
+/// Classifies which pool a job with these statements belongs on: read-only
+/// statements can run on any read worker in parallel, anything else (a
+/// write, or a `BEGIN`/`COMMIT`/`ROLLBACK`) must go to the single writer.
+fn classify(stmts: &Statements) -> WorkerKind {
+    if stmts.is_readonly() {
+        WorkerKind::Read
+    } else {
+        WorkerKind::Write
     }
 }
 
 struct Worker {
+    kind: WorkerKind,
     global_fifo: crossbeam::channel::Receiver<Job>,
+    /// only set for the write worker: batches are bulk writes, so they share
+    /// the single writer rather than getting their own pool.
+    batch_fifo: Option<crossbeam::channel::Receiver<JobBatch>>,
     db_conn: WalConnection,
     id: usize,
+    retry_policy: RetryPolicy,
+    metrics: Arc<WorkerMetrics>,
 }
 
 impl Worker {
+    /// Runs `stmts`, retrying on a transient `SQLITE_BUSY`/`SQLITE_LOCKED`
+    /// error with exponential backoff. `retryable` must be `false` for any
+    /// statement batch that may have already applied writes the database
+    /// won't let us safely redo.
+    fn perform_with_retry(&self, stmts: &Statements, retryable: bool) -> QueryResult {
+        self.retry(stmts, retryable, Self::perform_oneshot)
+    }
+
+    /// Like [`Worker::perform_with_retry`], but prepares `stmts` via the
+    /// statement cache instead of fresh each time. Used by
+    /// [`Worker::perform_batch`], where the same SQL text is often repeated
+    /// many times (e.g. a bulk insert), so batched writes get the same
+    /// busy/locked resilience as everything else.
+    fn perform_with_retry_cached(&self, stmts: &Statements, retryable: bool) -> QueryResult {
+        self.retry(stmts, retryable, Self::perform_oneshot_cached)
+    }
+
+    fn retry(
+        &self,
+        stmts: &Statements,
+        retryable: bool,
+        run: impl Fn(&Self, &Statements) -> QueryResult,
+    ) -> QueryResult {
+        let started = Instant::now();
+        let mut attempt = 0;
+        let result = loop {
+            match run(self, stmts) {
+                Err(e) if retryable
+                    && e.code() == ErrorCode::TxBusy
+                    && attempt < self.retry_policy.max_retries =>
+                {
+                    let delay = backoff_delay(&self.retry_policy, attempt);
+                    tracing::warn!(
+                        "busy/locked, retrying in {:?} (attempt {}/{})",
+                        delay,
+                        attempt + 1,
+                        self.retry_policy.max_retries
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                other => break other,
+            }
+        };
+        self.metrics.record_query(started.elapsed());
+        result
+    }
+
     fn perform_oneshot(&self, stmts: &Statements) -> QueryResult {
-        let mut result = vec![];
         let mut prepared = self.db_conn.prepare(&stmts.stmts)?;
-        let col_names: Vec<String> = prepared
+        Self::run_prepared(&mut prepared)
+    }
+
+    /// Like [`Worker::perform_oneshot`], but reuses a cached prepared
+    /// statement for `stmts.stmts` across calls instead of preparing it
+    /// fresh every time. Used by [`Worker::perform_batch`], where the same
+    /// SQL text is often repeated many times (e.g. a bulk insert).
+    fn perform_oneshot_cached(&self, stmts: &Statements) -> QueryResult {
+        let mut prepared = self.db_conn.prepare_cached(&stmts.stmts)?;
+        Self::run_prepared(&mut prepared)
+    }
+
+    fn run_prepared(prepared: &mut rusqlite::Statement) -> QueryResult {
+        let columns: Vec<ColumnMeta> = prepared
             .column_names()
             .iter()
-            .map(|s| s.to_string())
+            .map(|name| ColumnMeta {
+                name: name.to_string(),
+            })
             .collect();
-        //FIXME(sarna): the code below was ported as-is,
-        // but once we switch to gathering whole rows in the result vector
-        // instead of single values, Statement::query_map is a more convenient
-        // interface (it also implements Iter).
-        let mut rows = prepared.query([])?;
-        while let Some(row) = rows.next()? {
-            for (i, name) in col_names.iter().enumerate() {
-                result.push(format!("{} = {}", name, row.get::<usize, String>(i)?));
+
+        let ncols = columns.len();
+        let rows = prepared
+            .query_map([], |row| {
+                (0..ncols)
+                    .map(|i| row.get_ref(i).map(query::Value::from))
+                    .collect::<rusqlite::Result<Vec<query::Value>>>()
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(QueryResponse::Rows { columns, rows })
+    }
+
+    /// Runs every statement in `stmts` in order on this worker's connection,
+    /// returning one [`QueryResult`] per statement, retrying each on a
+    /// transient busy/locked error the same way a oneshot job would. Unless
+    /// `best_effort` is set, the whole batch runs under one implicit
+    /// transaction: the first failure rolls everything back and every later
+    /// statement is reported as skipped rather than being run.
+    fn perform_batch(&self, stmts: &[Statements], best_effort: bool) -> Vec<QueryResult> {
+        if !best_effort {
+            // no write has happened yet, so a busy/locked BEGIN is always
+            // safe to retry, same as any other statement before the first one.
+            if let Err(e) = self.perform_with_retry_cached(&Statements::new("BEGIN"), true) {
+                return stmts
+                    .iter()
+                    .map(|_| Err(QueryError::new(e.code(), anyhow::anyhow!("{e}"))))
+                    .collect();
+            }
+        }
+
+        let mut results = Vec::with_capacity(stmts.len());
+        let mut failed = false;
+        // once a write has applied within this batch's implicit transaction,
+        // a later busy/locked error must not be retried, for the same reason
+        // as in an interactive transaction: we can't tell whether it's the
+        // retry or the original attempt that will stick.
+        let mut has_written = false;
+        for s in stmts {
+            if !best_effort && failed {
+                results.push(Err(QueryError::new(
+                    ErrorCode::SQLError,
+                    anyhow::anyhow!("skipped: an earlier statement in the batch failed"),
+                )));
+                continue;
+            }
+            let retryable = s.is_readonly() || !has_written;
+            let result = self.perform_with_retry_cached(s, retryable);
+            if result.is_ok() && !s.is_readonly() {
+                has_written = true;
+            }
+            if result.is_err() {
+                failed = true;
             }
+            results.push(result);
         }
 
-        Ok(QueryResponse::ResultSet(result))
+        if !best_effort {
+            let _ = self
+                .db_conn
+                .execute(if failed { "ROLLBACK;" } else { "COMMIT;" }, ());
+        }
+
+        results
+    }
+
+    /// Runs a [`JobBatch`] and sends its results back, in order, on its
+    /// responder.
+    fn handle_batch(&self, batch: JobBatch) {
+        let results = self.perform_batch(&batch.statements, batch.best_effort);
+        let _ = batch.responder.send(results);
     }
 
     fn handle_transaction(&self, job: Job) {
+        self.metrics.record_txn_opened();
         let (sender, receiver) = crossbeam::channel::unbounded();
         job.scheduler_sender
             .send(UpdateStateMessage::TxnBegin(job.client_id, sender))
             .unwrap();
         let mut stmts = job.statements;
+        // depth 0 means no transaction is open yet (the outermost BEGIN is
+        // still the next statement to run); depth 1 is the outermost BEGIN,
+        // and every further nested BEGIN bumps it past that.
+        let mut depth: u32 = 0;
+        // once the transaction has applied a write, a busy/locked error on a
+        // later statement must not be retried: we can't tell whether it's the
+        // retry or the original attempt that will stick, and redoing a write
+        // risks applying it twice.
+        let mut has_written = false;
 
         let txn_timeout = Instant::now() + Duration::from_secs(TXN_TIMEOUT_SECS as _);
 
         let mut responder = job.responder;
         loop {
-            let message = self.perform_oneshot(&stmts);
-            let is_err = message.is_err();
+            let before = match depth {
+                0 => State::Start,
+                1 => State::TxnOpened,
+                _ => State::TxnOpenedNested,
+            };
+            let next_state = stmts.state(before);
+
+            // A nested BEGIN/COMMIT/ROLLBACK is rewritten into the matching
+            // SAVEPOINT statement before running it, so sqlite never sees a
+            // second top-level BEGIN.
+            let rewritten = match next_state {
+                State::TxnOpened if depth == 0 => {
+                    // the outermost BEGIN, run verbatim.
+                    depth += 1;
+                    None
+                }
+                State::TxnOpenedNested if stmts.is_begin() => {
+                    depth += 1;
+                    Some(Statements::new(format!("SAVEPOINT sp_{depth}")))
+                }
+                State::TxnClosedNested if stmts.is_rollback() => {
+                    let sp = depth;
+                    depth -= 1;
+                    Some(Statements::new(format!("ROLLBACK TO sp_{sp}")))
+                }
+                State::TxnClosedNested => {
+                    let sp = depth;
+                    depth -= 1;
+                    Some(Statements::new(format!("RELEASE sp_{sp}")))
+                }
+                _ => None,
+            };
+
+            let retryable = stmts.is_readonly() || !has_written;
+            let message = self.perform_with_retry(rewritten.as_ref().unwrap_or(&stmts), retryable);
+            let ok = message.is_ok();
+            if ok && !stmts.is_readonly() && !stmts.is_begin() {
+                has_written = true;
+            }
 
             let _ = responder.send(message);
 
-            match stmts.state(State::TxnOpened) {
-                State::TxnClosed if !is_err => {
-                    // the transaction was closed successfully
+            match next_state {
+                State::TxnClosed if ok => {
+                    // the outermost transaction was closed successfully
+                    if stmts.is_rollback() {
+                        self.metrics.record_txn_rolled_back();
+                    } else {
+                        self.metrics.record_txn_committed();
+                    }
                     job.scheduler_sender
                         .send(UpdateStateMessage::TxnEnded(job.client_id))
                         .unwrap();
@@ -126,6 +496,9 @@ impl Worker {
                 }
                 _ => {
                     // Let the database handle any other state
+                    job.scheduler_sender
+                        .send(UpdateStateMessage::TxnDepth(job.client_id, depth))
+                        .unwrap();
                     job.scheduler_sender
                         .send(UpdateStateMessage::Ready(job.client_id))
                         .unwrap();
@@ -136,7 +509,11 @@ impl Worker {
                         }
                         Err(_) => {
                             tracing::warn!("rolling back transaction!");
+                            // a plain ROLLBACK discards every SAVEPOINT along
+                            // with the outer transaction, tearing down the
+                            // whole stack regardless of depth.
                             let _ = self.db_conn.execute("ROLLBACK TRANSACTION;", ());
+                            self.metrics.record_txn_timed_out();
                             // FIXME: potential data race with Ready issued before.
                             job.scheduler_sender
                                 .send(UpdateStateMessage::TxnTimeout(job.client_id))
@@ -149,24 +526,153 @@ impl Worker {
         }
     }
 
+    fn handle_job(&self, job: Job) {
+        tracing::debug!(
+            "executing job `{:?}` on {:?} worker {}",
+            job.statements,
+            self.kind,
+            self.id
+        );
+
+        // This is an interactive transaction.
+        if let State::TxnOpened = job.statements.state(State::Start) {
+            self.handle_transaction(job)
+        } else {
+            // Any other state falls in this branch, even invalid: we let sqlite deal with the
+            // error handling. A oneshot job is a single statement batch with
+            // no prior writes to protect, so it's always safe to retry.
+            let m = self.perform_with_retry(&job.statements, true);
+            let _ = job.responder.send(m);
+            job.scheduler_sender
+                .send(UpdateStateMessage::Ready(job.client_id))
+                .unwrap();
+        }
+
+        tracing::debug!("job finished on {:?} worker {}", self.kind, self.id);
+    }
+
     fn run(self) {
-        while let Ok(job) = self.global_fifo.recv() {
-            tracing::debug!("executing job `{:?}` on worker {}", job.statements, self.id);
-
-            // This is an interactive transaction.
-            if let State::TxnOpened = job.statements.state(State::Start) {
-                self.handle_transaction(job)
-            } else {
-                // Any other state falls in this branch, even invalid: we let sqlite deal with the
-                // error handling.
-                let m = self.perform_oneshot(&job.statements);
-                let _ = job.responder.send(m);
-                job.scheduler_sender
-                    .send(UpdateStateMessage::Ready(job.client_id))
-                    .unwrap();
+        // Only the write worker has a `batch_fifo`: fall back to a plain
+        // blocking recv on the common path so read workers don't pay for a
+        // `Select` they never need.
+        let Some(batch_fifo) = self.batch_fifo.as_ref() else {
+            while let Ok(job) = self.global_fifo.recv() {
+                self.handle_job(job);
+            }
+            return;
+        };
+
+        let mut select = crossbeam::channel::Select::new();
+        let job_idx = select.recv(&self.global_fifo);
+        let batch_idx = select.recv(batch_fifo);
+        loop {
+            let oper = select.select();
+            match oper.index() {
+                i if i == job_idx => match oper.recv(&self.global_fifo) {
+                    Ok(job) => self.handle_job(job),
+                    Err(_) => break,
+                },
+                i if i == batch_idx => match oper.recv(batch_fifo) {
+                    Ok(batch) => self.handle_batch(batch),
+                    Err(_) => break,
+                },
+                _ => unreachable!(),
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(retry_policy: RetryPolicy) -> Worker {
+        let (_, global_fifo) = crossbeam::channel::unbounded();
+        Worker {
+            kind: WorkerKind::Write,
+            global_fifo,
+            batch_fifo: None,
+            db_conn: WalConnection::open(":memory:").unwrap(),
+            id: 0,
+            retry_policy,
+            metrics: Arc::new(WorkerMetrics::default()),
+        }
+    }
+
+    #[test]
+    fn classify_routes_reads_and_writes_to_the_matching_pool() {
+        assert_eq!(classify(&Statements::new("SELECT 1")), WorkerKind::Read);
+        assert_eq!(
+            classify(&Statements::new("INSERT INTO t VALUES (1)")),
+            WorkerKind::Write
+        );
+        assert_eq!(classify(&Statements::new("BEGIN")), WorkerKind::Write);
+    }
 
-            tracing::debug!("job finished on worker {}", self.id);
+    #[test]
+    fn backoff_delay_doubles_every_attempt_plus_a_little_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(10),
+        };
+        for attempt in 0..4 {
+            let delay = backoff_delay(&policy, attempt);
+            let base = policy.base_delay * 2u32.pow(attempt);
+            assert!(delay >= base, "attempt {attempt}: {delay:?} < {base:?}");
+            assert!(
+                delay < base + Duration::from_millis(10),
+                "attempt {attempt}: {delay:?} >= {base:?} + 10ms jitter cap"
+            );
         }
     }
+
+    #[test]
+    fn perform_batch_rolls_back_everything_after_the_first_failure() {
+        let w = worker(RetryPolicy::default());
+        w.db_conn.execute("CREATE TABLE t (x INTEGER)", ()).unwrap();
+
+        let results = w.perform_batch(
+            &[
+                Statements::new("INSERT INTO t VALUES (1)"),
+                Statements::new("INSERT INTO no_such_table VALUES (1)"),
+                Statements::new("INSERT INTO t VALUES (2)"),
+            ],
+            false,
+        );
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_err(), "statement after a failure is skipped, not run");
+
+        let count: i64 = w
+            .db_conn
+            .query_row("SELECT COUNT(*) FROM t", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0, "the whole batch rolled back, including the earlier success");
+    }
+
+    #[test]
+    fn perform_batch_best_effort_keeps_going_and_keeps_every_success() {
+        let w = worker(RetryPolicy::default());
+        w.db_conn.execute("CREATE TABLE t (x INTEGER)", ()).unwrap();
+
+        let results = w.perform_batch(
+            &[
+                Statements::new("INSERT INTO t VALUES (1)"),
+                Statements::new("INSERT INTO no_such_table VALUES (1)"),
+                Statements::new("INSERT INTO t VALUES (2)"),
+            ],
+            true,
+        );
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok(), "best_effort runs every statement regardless of earlier failures");
+
+        let count: i64 = w
+            .db_conn
+            .query_row("SELECT COUNT(*) FROM t", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2, "each best_effort statement commits on its own");
+    }
 }
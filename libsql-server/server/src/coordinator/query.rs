@@ -0,0 +1,121 @@
+use std::fmt;
+
+use rusqlite::types::ValueRef;
+
+/// Classifies why a query failed, so that callers can decide whether to
+/// retry, surface a user-facing error, or treat it as an internal bug.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// sqlite rejected the statement outright (syntax error, constraint
+    /// violation, missing table, ...).
+    SQLError,
+    /// the database was busy or locked by another connection.
+    TxBusy,
+    /// the transaction this query belonged to timed out.
+    TxTimeout,
+}
+
+/// An error that occurred while executing a [`Statements`](super::statements::Statements) batch.
+#[derive(Debug)]
+pub struct QueryError {
+    code: ErrorCode,
+    source: anyhow::Error,
+}
+
+impl QueryError {
+    pub fn new(code: ErrorCode, source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            code,
+            source: source.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// The name of one column in a [`QueryResponse::Rows`].
+#[derive(Debug, Clone)]
+pub struct ColumnMeta {
+    pub name: String,
+}
+
+/// A single SQLite cell, modeled after sqlite's storage classes rather than
+/// forced through a single Rust type (the old stringified representation
+/// errored out on anything but `TEXT`-affinity columns).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(v: ValueRef<'_>) -> Self {
+        match v {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(i) => Value::Integer(i),
+            ValueRef::Real(f) => Value::Real(f),
+            ValueRef::Text(t) => Value::Text(String::from_utf8_lossy(t).into_owned()),
+            ValueRef::Blob(b) => Value::Blob(b.to_vec()),
+        }
+    }
+}
+
+/// The result of executing a batch of statements.
+#[derive(Debug)]
+pub enum QueryResponse {
+    /// rows returned by a read statement, one [`Value`] per column per row.
+    Rows {
+        columns: Vec<ColumnMeta>,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+pub type QueryResult = Result<QueryResponse, QueryError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_from_value_ref_preserves_storage_class() {
+        assert_eq!(Value::from(ValueRef::Null), Value::Null);
+        assert_eq!(Value::from(ValueRef::Integer(42)), Value::Integer(42));
+        assert_eq!(Value::from(ValueRef::Real(1.5)), Value::Real(1.5));
+        assert_eq!(
+            Value::from(ValueRef::Text(b"hello")),
+            Value::Text("hello".to_string())
+        );
+        assert_eq!(
+            Value::from(ValueRef::Blob(&[1, 2, 3])),
+            Value::Blob(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn value_from_value_ref_lossily_decodes_non_utf8_text() {
+        // sqlite's TEXT affinity doesn't guarantee valid UTF-8; invalid bytes
+        // are replaced rather than propagating a decode error.
+        let invalid_utf8 = &[0xff, 0xfe][..];
+        match Value::from(ValueRef::Text(invalid_utf8)) {
+            Value::Text(s) => assert!(s.contains('\u{FFFD}')),
+            other => panic!("expected Value::Text, got {other:?}"),
+        }
+    }
+}
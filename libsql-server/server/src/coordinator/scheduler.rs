@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+use crate::job::Job;
+
+/// A client identifier, used by the scheduler to track per-connection
+/// transaction state across jobs handled by different workers.
+pub type ClientId = u64;
+
+/// Identifies a job held in a [`DelayedQueue`], so it can later be cancelled.
+pub type JobId = u64;
+
+struct DelayedQueueInner {
+    /// `(due, id)` pairs only, ordered earliest-due-first via `Reverse`; the
+    /// job itself lives in `jobs` so the heap doesn't need `Job: Ord`.
+    heap: BinaryHeap<Reverse<(Instant, JobId)>>,
+    jobs: HashMap<JobId, Job>,
+    next_id: JobId,
+}
+
+/// Holds jobs that aren't due yet, ordered by `run_at`, instead of forwarding
+/// them straight into a worker's fifo. A timer task drains whatever is due
+/// and waits on `due_soon` in between, so it doesn't have to busy-poll.
+pub struct DelayedQueue {
+    inner: Mutex<DelayedQueueInner>,
+    due_soon: Condvar,
+}
+
+impl DelayedQueue {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(DelayedQueueInner {
+                heap: BinaryHeap::new(),
+                jobs: HashMap::new(),
+                next_id: 0,
+            }),
+            due_soon: Condvar::new(),
+        }
+    }
+
+    /// Schedules `job` to run at `run_at`. Returns an id that can later be
+    /// passed to [`DelayedQueue::cancel`].
+    pub fn schedule(&self, run_at: Instant, job: Job) -> JobId {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.heap.push(Reverse((run_at, id)));
+        inner.jobs.insert(id, job);
+        drop(inner);
+        // the new job may be due sooner than whatever the timer was sleeping for.
+        self.due_soon.notify_one();
+        id
+    }
+
+    /// Cancels a pending job. Returns `false` if it already ran, was already
+    /// cancelled, or `id` is unknown. The heap entry is left in place and
+    /// skipped lazily once it comes due.
+    pub fn cancel(&self, id: JobId) -> bool {
+        self.inner.lock().unwrap().jobs.remove(&id).is_some()
+    }
+
+    /// Removes and returns every job due at or before `now`, along with the
+    /// next due time still pending (if any).
+    pub fn drain_due(&self, now: Instant) -> (Vec<Job>, Option<Instant>) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut due = Vec::new();
+        while let Some(&Reverse((at, id))) = inner.heap.peek() {
+            if at > now {
+                break;
+            }
+            inner.heap.pop();
+            if let Some(job) = inner.jobs.remove(&id) {
+                due.push(job);
+            }
+        }
+        let next = inner.heap.peek().map(|Reverse((at, _))| *at);
+        (due, next)
+    }
+
+    /// Sleeps until `deadline`, or up to an hour if there's nothing pending,
+    /// waking early if a job is scheduled in the meantime.
+    pub fn wait_until(&self, deadline: Option<Instant>) {
+        let guard = self.inner.lock().unwrap();
+        // a job scheduled after the caller's `drain_due` but before this lock
+        // was taken sent its notification too early to be seen, so also wake
+        // for whatever is pending now.
+        let pending = guard.heap.peek().map(|Reverse((at, _))| *at);
+        let deadline = match (deadline, pending) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let timeout = match deadline {
+            Some(at) => at.saturating_duration_since(Instant::now()),
+            None => std::time::Duration::from_secs(3600),
+        };
+        let _ = self.due_soon.wait_timeout(guard, timeout);
+    }
+}
+
+impl Default for DelayedQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::coordinator::statements::Statements;
+
+    use super::*;
+
+    fn dummy_job() -> Job {
+        let (responder, _) = crossbeam::channel::unbounded();
+        let (scheduler_sender, _) = crossbeam::channel::unbounded();
+        Job {
+            client_id: 0,
+            statements: Statements::new("SELECT 1"),
+            responder,
+            scheduler_sender,
+            run_at: None,
+        }
+    }
+
+    #[test]
+    fn drain_due_only_returns_jobs_at_or_before_now() {
+        let q = DelayedQueue::new();
+        let now = Instant::now();
+        q.schedule(now - Duration::from_secs(1), dummy_job());
+        q.schedule(now + Duration::from_secs(60), dummy_job());
+
+        let (due, next) = q.drain_due(now);
+
+        assert_eq!(due.len(), 1);
+        assert!(next.is_some());
+        // the still-pending job wasn't due yet: draining again with a
+        // far-future `now` should find exactly the one job left.
+        let (due, next) = q.drain_due(now + Duration::from_secs(120));
+        assert_eq!(due.len(), 1);
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn drain_due_returns_jobs_earliest_first() {
+        let q = DelayedQueue::new();
+        let now = Instant::now();
+        let mut later = dummy_job();
+        later.client_id = 2;
+        let mut sooner = dummy_job();
+        sooner.client_id = 1;
+        q.schedule(now + Duration::from_millis(20), later);
+        q.schedule(now + Duration::from_millis(10), sooner);
+
+        let (due, _) = q.drain_due(now + Duration::from_secs(1));
+        // both are due, and come out in due-time order, not insertion order.
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].client_id, 1);
+        assert_eq!(due[1].client_id, 2);
+    }
+
+    #[test]
+    fn cancel_removes_a_pending_job_before_it_comes_due() {
+        let q = DelayedQueue::new();
+        let now = Instant::now();
+        let id = q.schedule(now + Duration::from_secs(60), dummy_job());
+
+        assert!(q.cancel(id));
+        // cancelling twice, or an unknown id, reports no-op rather than panicking.
+        assert!(!q.cancel(id));
+        assert!(!q.cancel(id + 1));
+
+        let (due, next) = q.drain_due(now + Duration::from_secs(120));
+        assert!(due.is_empty());
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn wait_until_returns_promptly_for_a_past_deadline() {
+        let q = DelayedQueue::new();
+        let started = Instant::now();
+        q.wait_until(Some(started - Duration::from_secs(1)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
+
+/// Messages sent from a [`Worker`](super::Worker) back to the scheduler to
+/// report transaction lifecycle events for a given client.
+#[derive(Debug)]
+pub enum UpdateStateMessage {
+    /// the client opened an interactive transaction; further statements for
+    /// this client must be sent to `sender` so they land on the same worker.
+    TxnBegin(ClientId, crossbeam::channel::Sender<Job>),
+    /// the client's transaction was closed (committed or rolled back).
+    TxnEnded(ClientId),
+    /// the client's transaction depth changed, e.g. after a nested
+    /// `BEGIN`/`COMMIT`/`ROLLBACK` was turned into a `SAVEPOINT`.
+    TxnDepth(ClientId, u32),
+    /// the worker is done processing the current job and ready for more work.
+    Ready(ClientId),
+    /// the client's transaction timed out and was rolled back.
+    TxnTimeout(ClientId),
+}
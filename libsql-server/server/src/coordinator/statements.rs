@@ -0,0 +1,150 @@
+/// The transaction state that results from running a batch of statements,
+/// relative to the state the connection was in beforehand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// no transaction is open.
+    Start,
+    /// the outermost transaction was opened and is awaiting further statements.
+    TxnOpened,
+    /// a `BEGIN` was seen while a transaction was already open: a nested scope,
+    /// to be implemented with a `SAVEPOINT`.
+    TxnOpenedNested,
+    /// the outermost transaction was closed by this batch.
+    TxnClosed,
+    /// a `COMMIT`/`ROLLBACK` was seen while more than one scope was open: only
+    /// the innermost `SAVEPOINT` closes, the outer transaction stays open.
+    TxnClosedNested,
+    /// let sqlite report the error; we don't try to interpret this state.
+    Invalid,
+}
+
+/// A batch of raw SQL text submitted together as a single [`Job`](crate::job::Job).
+#[derive(Debug, Clone)]
+pub struct Statements {
+    pub stmts: String,
+}
+
+impl Statements {
+    pub fn new(stmts: impl Into<String>) -> Self {
+        Self {
+            stmts: stmts.into(),
+        }
+    }
+
+    /// Given the state the connection was in before running `self` (`before`
+    /// is [`State::Start`], [`State::TxnOpened`] or [`State::TxnOpenedNested`]
+    /// depending on the current transaction depth), returns the state it will
+    /// be in afterwards.
+    pub fn state(&self, before: State) -> State {
+        match before {
+            State::Start if self.is_begin() => State::TxnOpened,
+            State::TxnOpened if self.is_begin() => State::TxnOpenedNested,
+            State::TxnOpenedNested if self.is_begin() => State::TxnOpenedNested,
+            State::TxnOpened if self.is_commit() || self.is_rollback() => State::TxnClosed,
+            State::TxnOpenedNested if self.is_commit() || self.is_rollback() => {
+                State::TxnClosedNested
+            }
+            other => other,
+        }
+    }
+
+    /// Whether this batch opens a (possibly nested) transaction scope.
+    pub fn is_begin(&self) -> bool {
+        self.stmts.trim().to_uppercase().starts_with("BEGIN")
+    }
+
+    /// Whether this batch commits the innermost open scope.
+    pub fn is_commit(&self) -> bool {
+        self.stmts.trim().to_uppercase().starts_with("COMMIT")
+    }
+
+    /// Whether this batch rolls back the innermost open scope.
+    pub fn is_rollback(&self) -> bool {
+        self.stmts.trim().to_uppercase().starts_with("ROLLBACK")
+    }
+
+    /// Whether this batch of statements can be dispatched to any read-only
+    /// worker: true unless the batch starts with one of the write or
+    /// transaction keywords below. Only the leading keyword is checked, so a
+    /// later statement in the batch, or a write behind a `WITH` clause, is not
+    /// detected.
+    pub fn is_readonly(&self) -> bool {
+        const WRITE_KEYWORDS: &[&str] = &[
+            "INSERT", "UPDATE", "DELETE", "REPLACE", "CREATE", "DROP", "ALTER",
+            "VACUUM", "ATTACH", "DETACH", "BEGIN", "COMMIT", "ROLLBACK", "SAVEPOINT",
+            "RELEASE", "REINDEX",
+        ];
+        let upper = self.stmts.trim().to_uppercase();
+        !WRITE_KEYWORDS.iter().any(|kw| upper.starts_with(kw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stmts(sql: &str) -> Statements {
+        Statements::new(sql)
+    }
+
+    #[test]
+    fn begin_from_start_opens_the_outermost_transaction() {
+        assert_eq!(stmts("BEGIN").state(State::Start), State::TxnOpened);
+    }
+
+    #[test]
+    fn begin_while_already_open_nests() {
+        assert_eq!(
+            stmts("BEGIN").state(State::TxnOpened),
+            State::TxnOpenedNested
+        );
+        assert_eq!(
+            stmts("begin transaction").state(State::TxnOpenedNested),
+            State::TxnOpenedNested
+        );
+    }
+
+    #[test]
+    fn commit_or_rollback_at_depth_one_closes_the_outermost_transaction() {
+        assert_eq!(stmts("COMMIT").state(State::TxnOpened), State::TxnClosed);
+        assert_eq!(stmts("ROLLBACK").state(State::TxnOpened), State::TxnClosed);
+    }
+
+    #[test]
+    fn commit_or_rollback_while_nested_only_closes_the_inner_scope() {
+        assert_eq!(
+            stmts("COMMIT").state(State::TxnOpenedNested),
+            State::TxnClosedNested
+        );
+        assert_eq!(
+            stmts("ROLLBACK").state(State::TxnOpenedNested),
+            State::TxnClosedNested
+        );
+    }
+
+    #[test]
+    fn anything_else_leaves_the_state_unchanged() {
+        assert_eq!(stmts("SELECT 1").state(State::Start), State::Start);
+        assert_eq!(stmts("SELECT 1").state(State::TxnOpened), State::TxnOpened);
+        assert_eq!(
+            stmts("INSERT INTO t VALUES (1)").state(State::TxnOpenedNested),
+            State::TxnOpenedNested
+        );
+    }
+
+    #[test]
+    fn is_begin_commit_rollback_are_case_and_whitespace_insensitive() {
+        assert!(stmts("  begin ").is_begin());
+        assert!(stmts("Commit;").is_commit());
+        assert!(stmts("\nROLLBACK").is_rollback());
+        assert!(!stmts("SELECT 1").is_begin());
+    }
+
+    #[test]
+    fn is_readonly_rejects_write_and_transaction_keywords() {
+        assert!(stmts("SELECT * FROM t").is_readonly());
+        assert!(!stmts("INSERT INTO t VALUES (1)").is_readonly());
+        assert!(!stmts("BEGIN").is_readonly());
+        assert!(!stmts("SAVEPOINT sp_1").is_readonly());
+    }
+}
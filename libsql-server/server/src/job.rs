@@ -0,0 +1,35 @@
+use std::time::Instant;
+
+use crossbeam::channel::Sender;
+
+use crate::coordinator::query::QueryResult;
+use crate::coordinator::scheduler::{ClientId, UpdateStateMessage};
+use crate::coordinator::statements::Statements;
+
+/// A unit of work submitted to the [`Coordinator`](crate::coordinator::Coordinator):
+/// a batch of statements to run on behalf of a client, the channel to send the
+/// result back on, and the channel workers use to report transaction state
+/// changes to the scheduler.
+pub struct Job {
+    pub client_id: ClientId,
+    pub statements: Statements,
+    pub responder: Sender<QueryResult>,
+    pub scheduler_sender: Sender<UpdateStateMessage>,
+    /// if set and still in the future when the job is received, the job is
+    /// held in the coordinator's delayed queue instead of being dispatched
+    /// straight away.
+    pub run_at: Option<Instant>,
+}
+
+/// Several [`Statements`] submitted together to amortize channel and prepare
+/// overhead, e.g. for a bulk-insert client. Run on the write worker under a
+/// single implicit transaction, unless `best_effort` opts out of that.
+pub struct JobBatch {
+    pub client_id: ClientId,
+    pub statements: Vec<Statements>,
+    pub responder: Sender<Vec<QueryResult>>,
+    /// if `false` (the default), any failing statement rolls back every
+    /// result in the batch. If `true`, each statement is committed on its own
+    /// regardless of whether earlier ones failed.
+    pub best_effort: bool,
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+/// A connection to a database running in WAL mode.
+pub struct WalConnection {
+    conn: Connection,
+}
+
+impl WalConnection {
+    /// Opens a read-write connection and puts it in WAL mode.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Ok(Self { conn })
+    }
+
+    /// Opens a read-only connection. Used by the read pool, since WAL allows
+    /// any number of concurrent readers alongside a single writer.
+    pub fn open_readonly(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Self { conn })
+    }
+}
+
+impl std::ops::Deref for WalConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}